@@ -0,0 +1,207 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::{now_nanos, snapshot, Clock, Counter, Header};
+
+/// Number of linear sub-buckets carved out of each power-of-two range.
+/// Four bits of sub-bucket resolution bounds the relative error of any
+/// recorded value to roughly 1/16 (~6%), HdrHistogram-style.
+const SUBBUCKET_BITS: u32 = 4;
+const SUBBUCKET_COUNT: usize = 1 << SUBBUCKET_BITS;
+const EXPONENT_COUNT: usize = 64;
+const BUCKET_COUNT: usize = EXPONENT_COUNT * SUBBUCKET_COUNT;
+
+#[derive(Debug)]
+pub struct HistogramCounter {
+    headers: Header,
+    clock: Clock,
+    time_start: AtomicU64,
+    event_count: AtomicU64,
+    time_least: AtomicU64,
+    time_most: AtomicU64,
+    buckets: Vec<AtomicU64>,
+}
+
+impl Default for HistogramCounter {
+    fn default() -> Self {
+        HistogramCounter {
+            headers: Header::default(),
+            clock: Clock::default(),
+            time_start: AtomicU64::new(0),
+            event_count: AtomicU64::new(0),
+            time_least: AtomicU64::new(0),
+            time_most: AtomicU64::new(0),
+            buckets: (0..BUCKET_COUNT).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+}
+
+impl HistogramCounter {
+    /// Creates a `HistogramCounter` that times against `clock` instead of the default
+    /// `Clock::Monotonic`.
+    pub fn with_clock(clock: Clock) -> Self {
+        HistogramCounter { clock, ..Default::default() }
+    }
+
+    fn record(&self, value: u64) {
+        self.buckets[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        self.event_count.fetch_add(1, Ordering::Relaxed);
+
+        let mut least = self.time_least.load(Ordering::Relaxed);
+        while least == 0 || value < least {
+            match self.time_least.compare_exchange_weak(least, value, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => least = current,
+            }
+        }
+
+        let mut most = self.time_most.load(Ordering::Relaxed);
+        while value > most {
+            match self.time_most.compare_exchange_weak(most, value, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => most = current,
+            }
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.event_count.load(Ordering::Relaxed)
+    }
+
+    pub fn min(&self) -> u64 {
+        self.time_least.load(Ordering::Relaxed)
+    }
+
+    pub fn max(&self) -> u64 {
+        self.time_most.load(Ordering::Relaxed)
+    }
+
+    /// Walks the cumulative bucket counts to find the value at quantile `q` (0.0..=1.0).
+    pub fn percentile(&self, q: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+
+        let target = ((q * count as f64).ceil() as u64).max(1);
+        let mut cumulative = 0u64;
+        for (bucket, slot) in self.buckets.iter().enumerate() {
+            cumulative += slot.load(Ordering::Relaxed);
+            if cumulative >= target {
+                return bucket_lower_bound(bucket);
+            }
+        }
+
+        self.max()
+    }
+}
+
+impl Counter for HistogramCounter {
+    fn begin(&self) {
+        self.time_start.store(now_nanos(self.clock), Ordering::Release);
+    }
+
+    fn end(&self) {
+        let time_start = self.time_start.swap(0, Ordering::AcqRel);
+        if time_start == 0 {
+            return;
+        }
+
+        self.record(now_nanos(self.clock) - time_start);
+    }
+
+    fn set_elapsed(&self, elapsed: u64) {
+        if elapsed > 0 {
+            self.record(elapsed);
+        }
+    }
+
+    fn cancel(&self) {
+        self.time_start.store(0, Ordering::Release);
+    }
+
+    fn snapshot(&self) -> snapshot::CounterSnapshot {
+        snapshot::CounterSnapshot::Histogram {
+            count: self.count(),
+            min: self.min(),
+            max: self.max(),
+            buckets: self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect(),
+        }
+    }
+}
+
+fn bucket_index(value: u64) -> usize {
+    if value == 0 {
+        return 0;
+    }
+
+    let exponent = 63 - value.leading_zeros();
+    let range_start = 1u64 << exponent;
+    let offset = value - range_start;
+    let sub = if exponent >= SUBBUCKET_BITS {
+        offset >> (exponent - SUBBUCKET_BITS)
+    } else {
+        offset << (SUBBUCKET_BITS - exponent)
+    };
+
+    exponent as usize * SUBBUCKET_COUNT + (sub as usize).min(SUBBUCKET_COUNT - 1)
+}
+
+fn bucket_lower_bound(bucket: usize) -> u64 {
+    let exponent = (bucket / SUBBUCKET_COUNT) as u32;
+    let sub = (bucket % SUBBUCKET_COUNT) as u64;
+    let range_start = 1u64 << exponent;
+
+    if exponent >= SUBBUCKET_BITS {
+        range_start + (sub << (exponent - SUBBUCKET_BITS))
+    } else {
+        range_start + (sub >> (SUBBUCKET_BITS - exponent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_of_uniform_samples_returns_bucket_lower_bound() {
+        let counter = HistogramCounter::default();
+        for _ in 0..10 {
+            counter.set_elapsed(1000);
+        }
+
+        assert_eq!(counter.count(), 10);
+        assert_eq!(counter.min(), 1000);
+        assert_eq!(counter.max(), 1000);
+        assert_eq!(counter.percentile(0.5), bucket_lower_bound(bucket_index(1000)));
+        assert_eq!(counter.percentile(0.99), bucket_lower_bound(bucket_index(1000)));
+    }
+
+    #[test]
+    fn percentile_walks_cumulative_buckets_in_value_order() {
+        let counter = HistogramCounter::default();
+        for value in [10u64, 20, 30, 40, 100] {
+            counter.set_elapsed(value);
+        }
+
+        assert_eq!(counter.count(), 5);
+        assert_eq!(counter.min(), 10);
+        assert_eq!(counter.max(), 100);
+        // ceil(0.2 * 5) == 1, so p20 should land in the first (lowest) sample's bucket.
+        assert_eq!(counter.percentile(0.2), bucket_lower_bound(bucket_index(10)));
+        // p100 should land in the last (highest) sample's bucket.
+        assert_eq!(counter.percentile(1.0), bucket_lower_bound(bucket_index(100)));
+    }
+
+    #[test]
+    fn percentile_of_empty_histogram_is_zero() {
+        let counter = HistogramCounter::default();
+        assert_eq!(counter.percentile(0.5), 0);
+    }
+
+    #[test]
+    fn bucket_index_and_lower_bound_round_trip_power_of_two_boundaries() {
+        for exponent in 0u32..40 {
+            let value = 1u64 << exponent;
+            assert_eq!(bucket_lower_bound(bucket_index(value)), value);
+        }
+    }
+}
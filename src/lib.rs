@@ -1,19 +1,56 @@
-#![feature(no_more_cas)]
-
+pub mod histogram;
+pub mod rate;
 pub mod registry;
+pub mod sampler;
+pub mod sharded;
+pub mod snapshot;
+
+pub use histogram::HistogramCounter;
+pub use rate::RateCounter;
+pub use sampler::Runner;
+pub use sharded::{ShardedElapsedCounter, ShardedEventCounter};
+pub use snapshot::CounterSnapshot;
 
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::fmt::Debug;
-use libc::{clock_gettime, CLOCK_MONOTONIC, timespec, c_int};
-use std::convert::{TryFrom, TryInto};
+use libc::{clock_gettime, clockid_t, CLOCK_MONOTONIC, CLOCK_MONOTONIC_RAW, CLOCK_PROCESS_CPUTIME_ID, timespec, c_int};
 use atomic::Atomic;
+use crossbeam_utils::Backoff;
+
+/// Which clock a timing counter reads its timestamps from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clock {
+    /// Wall-clock elapsed time. The default for every timing counter.
+    Monotonic,
+    /// Like `Monotonic`, but not subject to NTP frequency/slew adjustment, for callers
+    /// that need a timestamp no outside process can nudge.
+    MonotonicRaw,
+    /// CPU time consumed by this process, for on-CPU profiling rather than wall time.
+    ProcessCpuTime,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::Monotonic
+    }
+}
+
+impl Clock {
+    fn id(self) -> clockid_t {
+        match self {
+            Clock::Monotonic => CLOCK_MONOTONIC,
+            Clock::MonotonicRaw => CLOCK_MONOTONIC_RAW,
+            Clock::ProcessCpuTime => CLOCK_PROCESS_CPUTIME_ID,
+        }
+    }
+}
 
 #[derive(Clone, Debug, Default)]
 pub struct Header {
     name: String,
 }
 
-pub trait Counter: Reset + Debug {
+pub trait Counter: Reset + Debug + Send + Sync {
     fn begin(&self) {}
     fn increment(&self) {}
     fn end(&self) {}
@@ -21,18 +58,51 @@ pub trait Counter: Reset + Debug {
     fn set_count(&self, _count: u64) {}
     fn cancel(&self) {}
     fn reset(&mut self) { self.reset_it() }
+    fn snapshot(&self) -> snapshot::CounterSnapshot { snapshot::CounterSnapshot::Unknown }
 }
 
 pub trait Reset {
     fn reset_it(&mut self);
 }
 
+/// Read access to a counter's running distribution, for counters that track one.
+pub trait Stats {
+    fn count(&self) -> u64;
+    fn min(&self) -> u64;
+    fn max(&self) -> u64;
+    fn mean(&self) -> f64;
+    fn variance(&self) -> f64;
+    fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+}
+
 impl<T> Reset for T where T: Default {
     fn reset_it(&mut self) {
         *self = T::default();
     }
 }
 
+/// `count`/`mean`/`m2` for Welford's online algorithm, swapped as a single unit so a
+/// concurrent reader can never observe `mean` updated while `count`/`m2` are still stale.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Moments {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Moments {
+    /// Folds one more sample `dt` into the running mean/variance.
+    fn accumulate(self, dt: f64) -> Self {
+        let count = self.count + 1;
+        let delta = dt - self.mean;
+        let mean = self.mean + delta / count as f64;
+        let m2 = self.m2 + delta * (dt - mean);
+        Moments { count, mean, m2 }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct EventCounter {
     headers: Header,
@@ -45,186 +115,283 @@ impl Counter for EventCounter {
     }
     fn set_count(&self, count: u64) { self.event_count.store(count, Ordering::SeqCst); }
 
+    fn snapshot(&self) -> snapshot::CounterSnapshot {
+        snapshot::CounterSnapshot::Count(self.event_count.load(Ordering::Acquire))
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct ElapsedCounter {
     headers: Header,
-    event_count: AtomicU64,
+    clock: Clock,
     time_start: AtomicU64,
-    time_total: AtomicU64,
     time_least: AtomicU64,
     time_most: AtomicU64,
-    mean: Atomic<f64>,
-    m2: Atomic<f64>
+    moments: Atomic<Moments>,
 }
 
-impl Counter for ElapsedCounter {
-    fn begin(&self) {
-        let mut ts = default_timespec();
-        unsafe {
-            let _ = get_time(&mut ts);
-        }
-            self.time_start.store(ts.tv_sec.try_into().unwrap(), Ordering::SeqCst);
-
+impl ElapsedCounter {
+    /// Creates an `ElapsedCounter` that times against `clock` instead of the default
+    /// `Clock::Monotonic`, e.g. `Clock::ProcessCpuTime` to measure on-CPU time.
+    pub fn with_clock(clock: Clock) -> Self {
+        ElapsedCounter { clock, ..Default::default() }
     }
 
-    fn end(&self) {
-        loop {
-            let time_start = self.time_start.load(Ordering::SeqCst);
-            if time_start > 0 {
-                let mut ts = default_timespec();
-                let _ = unsafe { get_time(&mut ts)};
-                let elapsed: u64 = u64::try_from(ts.tv_sec).unwrap() - time_start;
-                if self.time_start.compare_and_swap(time_start, 0, Ordering::SeqCst) != time_start {
-                    continue;
-                }
-
-                let time_least = self.time_least.load(Ordering::SeqCst);
-                if time_least > elapsed {
-                    if self.time_least.compare_and_swap(time_least, elapsed, Ordering::SeqCst) != time_least {
-                        continue
-                    }
-
-                }
-
-                if time_least == 0 || time_least > elapsed {
-                    self.event_count.fetch_add(1u64, Ordering::SeqCst);
-                    self.time_total.fetch_add(elapsed, Ordering::SeqCst);
-                }
-
-                let time_most = self.time_most.load(Ordering::SeqCst);
-                if time_most < elapsed {
-                    if self.time_most.compare_and_swap(time_most, elapsed, Ordering::SeqCst) != time_most {
-                        continue;
-                    }
-                }
-
-                let mean = self.mean.load(Ordering::SeqCst);
-                let event_count = self.event_count.load(Ordering::SeqCst);
-                let delta_interval = dt - mean;
-                if self.mean.compare_and_swap(mean, mean + delta_interval * event_count) != mean {
-                    continue
-                }
-
-                let m2 = self.m2.load(Ordering::SeqCst);
-                if self.m2.compare_and_swap(m2, m2 + dt - mean) != m2 {
-                    continue;
-                }
+    // Folds `elapsed` into `time_least`/`time_most`, then into `moments` as a
+    // single atomically-swapped unit so a concurrent `Stats` reader can never see
+    // `mean` updated while `count`/`m2` are still stale.
+    fn record(&self, elapsed: u64) {
+        let backoff = Backoff::new();
+        let mut time_least = self.time_least.load(Ordering::Acquire);
+        while time_least == 0 || elapsed < time_least {
+            match self.time_least.compare_exchange_weak(time_least, elapsed, Ordering::Release, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(current) => { time_least = current; backoff.snooze(); }
             }
         }
-    }
-
-    fn set_elapsed(&self, elapsed: u64) {
-        if elapsed > 0 {
-            self.event_count.fetch_add(1, Ordering::SeqCst);
-            self.time_total.fetch_add(elapsed, Ordering::SeqCst);
 
-            if self.time_least > elapsed {
-                self.time_least.store(elapsed, Ordering::SeqCst);
+        let backoff = Backoff::new();
+        let mut time_most = self.time_most.load(Ordering::Acquire);
+        while elapsed > time_most {
+            match self.time_most.compare_exchange_weak(time_most, elapsed, Ordering::Release, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(current) => { time_most = current; backoff.snooze(); }
             }
+        }
 
-            if self.time_most < elapsed {
-                self.time_most.store(elapsed, Ordering::SeqCst);
+        // Nanoseconds throughout, so `mean`/`variance` stay in the same unit as
+        // `min`/`max` instead of reporting a distribution in mixed units.
+        let dt = elapsed as f64;
+        let backoff = Backoff::new();
+        loop {
+            let moments = self.moments.load(Ordering::Acquire);
+            let next = moments.accumulate(dt);
+            if self.moments.compare_exchange_weak(moments, next, Ordering::Release, Ordering::Acquire).is_ok() {
+                break;
             }
+            backoff.snooze();
+        }
+    }
+}
 
-            let dt = elapsed as f64 / 1e6f64;
-
-            loop {
-                let mean = self.mean.load(Ordering::SeqCst);
-                let event_count = self.event_count.load(Ordering::SeqCst);
-                let delta_interval = dt - mean;
-                if self.mean.compare_and_swap(mean, mean + delta_interval * event_count) != mean {
-                    continue
-                }
+impl Stats for ElapsedCounter {
+    fn count(&self) -> u64 { self.moments.load(Ordering::Acquire).count }
+    fn min(&self) -> u64 { self.time_least.load(Ordering::Acquire) }
+    fn max(&self) -> u64 { self.time_most.load(Ordering::Acquire) }
+    fn mean(&self) -> f64 { self.moments.load(Ordering::Acquire).mean }
+    fn variance(&self) -> f64 {
+        let moments = self.moments.load(Ordering::Acquire);
+        if moments.count < 2 { return 0f64; }
+        moments.m2 / (moments.count - 1) as f64
+    }
+}
 
-                let m2 = self.m2.load(Ordering::SeqCst);
-                if self.m2.compare_and_swap(m2, m2 + dt - mean) != m2 {
-                    continue
-                }
+impl Counter for ElapsedCounter {
+    fn begin(&self) {
+        self.time_start.store(now_nanos(self.clock), Ordering::Release);
+    }
 
+    fn end(&self) {
+        let backoff = Backoff::new();
+        let time_start = loop {
+            let time_start = self.time_start.load(Ordering::Acquire);
+            if time_start == 0 {
+                // Nothing was started (or another caller already claimed it): nothing to record.
+                return;
             }
 
+            match self.time_start.compare_exchange_weak(time_start, 0, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break time_start,
+                Err(_) => backoff.snooze(),
+            }
+        };
 
-            self.time_start.store(0, Ordering::SeqCst);
+        let elapsed = now_nanos(self.clock) - time_start;
+        self.record(elapsed);
+    }
 
+    fn set_elapsed(&self, elapsed: u64) {
+        if elapsed > 0 {
+            self.record(elapsed);
+            self.time_start.store(0, Ordering::Release);
         }
     }
 
     fn cancel(&self) {
-        self.time_start.store(0, Ordering::SeqCst);
+        self.time_start.store(0, Ordering::Release);
     }
 
-
+    fn snapshot(&self) -> snapshot::CounterSnapshot {
+        snapshot::CounterSnapshot::Distribution {
+            count: self.count(),
+            min: self.min(),
+            max: self.max(),
+            mean: self.mean(),
+            variance: self.variance(),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct IntervalCounter {
     headers: Header,
+    clock: Clock,
     event_count: AtomicU64,
     time_event: AtomicU64,
     time_first: AtomicU64,
     time_last: AtomicU64,
     time_least: AtomicU64,
     time_most: AtomicU64,
-    mean: Atomic<f64>,
-    m2: Atomic<f64>
+    moments: Atomic<Moments>,
+}
+
+impl IntervalCounter {
+    /// Creates an `IntervalCounter` that times against `clock` instead of the default
+    /// `Clock::Monotonic`.
+    pub fn with_clock(clock: Clock) -> Self {
+        IntervalCounter { clock, ..Default::default() }
+    }
 }
 
 impl Counter for IntervalCounter {
     fn increment(&self) {
-        let mut ts = default_timespec();
-        let now = u64::try_from(unsafe { get_time(&mut ts) }).unwrap();
+        let now = now_nanos(self.clock);
 
-        loop {
-            let count = self.event_count.load(Ordering::SeqCst);
-            match count {
-                0 => self.time_first.store(now, Ordering::SeqCst),
-                1 => {
-                    let last_time = now - self.time_last.load(Ordering::SeqCst);
-                    self.time_least.store(last_time, Ordering::SeqCst);
-                    self.time_most.store(last_time, Ordering::SeqCst);
-                    self.mean.store(last_time as f64 / 1e6f64);
-                    self.m2.store(0);
-                    break;
-                },
-                co => {
-                    let interval = now - self.time_last.load(Ordering::SeqCst);
-                    if interval < self.time_least.load(Ordering::SeqCst) {
-                        self.time_least.store(interval, Ordering::SeqCst);
-                    }
-                    if interval > self.time_most.load(Ordering::SeqCst) {
-                        self.time_most.store(interval, Ordering::SeqCst);
-                    }
-
-                    let dt = interval as f64 / 1e6f64;
-                    let delta_interval = dt - self.mean.load(Ordering::SeqCst);
-                    let mean = delta_interval / co;
-                    self.mean.store(mean, Ordering::SeqCst);
-                    self.m2.store(delta_interval * (dt - mean), Ordering::SeqCst);
-                    break;
+        let time_last = self.time_last.swap(now, Ordering::AcqRel);
+        let count = self.event_count.fetch_add(1, Ordering::AcqRel);
+
+        if count == 0 {
+            // First call establishes the baseline timestamp; there's no interval yet.
+            self.time_first.store(now, Ordering::Release);
+            return;
+        }
+
+        // `now` is read before the swap, so a concurrent caller that read a later
+        // timestamp can swap it in between our clock read and our swap, leaving
+        // `time_last` newer than our own `now`: saturate instead of
+        // underflowing into a subtract-overflow panic (or a garbage ~u64::MAX interval).
+        let interval = now.saturating_sub(time_last);
+
+        if count == 1 {
+            // First interval: nothing to compare against yet, so seed least/most directly.
+            self.time_least.store(interval, Ordering::Release);
+            self.time_most.store(interval, Ordering::Release);
+        } else {
+            let backoff = Backoff::new();
+            let mut time_least = self.time_least.load(Ordering::Acquire);
+            while interval < time_least {
+                match self.time_least.compare_exchange_weak(time_least, interval, Ordering::Release, Ordering::Acquire) {
+                    Ok(_) => break,
+                    Err(current) => { time_least = current; backoff.snooze(); }
                 }
-            };
+            }
+
+            let backoff = Backoff::new();
+            let mut time_most = self.time_most.load(Ordering::Acquire);
+            while interval > time_most {
+                match self.time_most.compare_exchange_weak(time_most, interval, Ordering::Release, Ordering::Acquire) {
+                    Ok(_) => break,
+                    Err(current) => { time_most = current; backoff.snooze(); }
+                }
+            }
+        }
+
+        // Nanoseconds throughout, so `mean`/`variance` stay in the same unit as
+        // `min`/`max` instead of reporting a distribution in mixed units. `count`/
+        // `mean`/`m2` are folded into `moments` as a single atomically-swapped unit so
+        // a concurrent `Stats` reader can never see `mean` updated while `count`/`m2`
+        // are still stale.
+        let dt = interval as f64;
+        let backoff = Backoff::new();
+        loop {
+            let moments = self.moments.load(Ordering::Acquire);
+            let next = moments.accumulate(dt);
+            if self.moments.compare_exchange_weak(moments, next, Ordering::Release, Ordering::Acquire).is_ok() {
+                break;
+            }
+            backoff.snooze();
         }
+    }
 
-        self.time_last.store(now, Ordering::SeqCst);
-        self.event_count.fetch_add(1, Ordering::SeqCst);
+    fn snapshot(&self) -> snapshot::CounterSnapshot {
+        snapshot::CounterSnapshot::Distribution {
+            count: self.count(),
+            min: self.min(),
+            max: self.max(),
+            mean: self.mean(),
+            variance: self.variance(),
+        }
+    }
+}
 
+impl Stats for IntervalCounter {
+    fn count(&self) -> u64 { self.moments.load(Ordering::Acquire).count }
+    fn min(&self) -> u64 { self.time_least.load(Ordering::Acquire) }
+    fn max(&self) -> u64 { self.time_most.load(Ordering::Acquire) }
+    fn mean(&self) -> f64 { self.moments.load(Ordering::Acquire).mean }
+    fn variance(&self) -> f64 {
+        let moments = self.moments.load(Ordering::Acquire);
+        if moments.count < 2 { return 0f64; }
+        moments.m2 / (moments.count - 1) as f64
     }
 }
 
-pub unsafe fn get_time(timespec: *mut timespec) -> c_int {
-    clock_gettime(CLOCK_MONOTONIC, timespec)
+pub unsafe fn get_time(clock: Clock, timespec: *mut timespec) -> c_int {
+    clock_gettime(clock.id(), timespec)
 }
 
-fn default_timespec() -> timespec {
+pub(crate) fn default_timespec() -> timespec {
     timespec { tv_sec: 0i64, tv_nsec: 0i64}
 }
 
+/// Reads `clock` as a single `u64` of nanoseconds (`tv_sec * 1e9 + tv_nsec`), so
+/// sub-second measurements survive instead of being truncated away.
+pub(crate) fn now_nanos(clock: Clock) -> u64 {
+    let mut ts = default_timespec();
+    let _ = unsafe { get_time(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// A `CLOCK_MONOTONIC` instant in nanoseconds, for gating/windowing code (such as
+/// `RateCounter` and `Runner`) that always wants wall-clock time regardless of which
+/// clock an individual counter was configured to record against.
+pub(crate) fn monotonic_nanos() -> u64 {
+    now_nanos(Clock::Monotonic)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn elapsed_counter_tracks_welford_mean_and_variance() {
+        let counter = ElapsedCounter::default();
+        counter.set_elapsed(100);
+        counter.set_elapsed(200);
+        counter.set_elapsed(300);
+
+        assert_eq!(counter.count(), 3);
+        assert_eq!(counter.min(), 100);
+        assert_eq!(counter.max(), 300);
+        assert_eq!(counter.mean(), 200f64);
+        // Sample variance (n - 1 denominator) of [100, 200, 300] is 10000.
+        assert_eq!(counter.variance(), 10_000f64);
+        assert_eq!(counter.stddev(), 100f64);
+    }
+
+    #[test]
+    fn elapsed_counter_variance_is_zero_below_two_samples() {
+        let counter = ElapsedCounter::default();
+        assert_eq!(counter.variance(), 0f64);
+
+        counter.set_elapsed(42);
+        assert_eq!(counter.count(), 1);
+        assert_eq!(counter.mean(), 42f64);
+        assert_eq!(counter.variance(), 0f64);
+    }
 }
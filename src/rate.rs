@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use crate::monotonic_nanos;
+use crate::{snapshot, Counter, Header};
+
+/// Number of buckets the sliding window is divided into; each bucket covers
+/// `window / BUCKET_COUNT` of wall-clock time.
+const BUCKET_COUNT: u64 = 10;
+
+/// Events-per-second over a trailing window, kept as a ring of per-bucket counts that
+/// age out automatically as the active bucket advances with the monotonic clock.
+#[derive(Debug)]
+pub struct RateCounter {
+    headers: Header,
+    window_nanos: u64,
+    bucket_nanos: u64,
+    buckets: Vec<AtomicU64>,
+    current_bucket: AtomicU64,
+    created_nanos: u64,
+}
+
+impl RateCounter {
+    pub fn with_window(window: Duration) -> Self {
+        let window_nanos = window.as_nanos() as u64;
+        RateCounter {
+            headers: Header::default(),
+            window_nanos,
+            bucket_nanos: (window_nanos / BUCKET_COUNT).max(1),
+            buckets: (0..BUCKET_COUNT).map(|_| AtomicU64::new(0)).collect(),
+            current_bucket: AtomicU64::new(0),
+            created_nanos: monotonic_nanos(),
+        }
+    }
+
+    fn bucket_index(&self, bucket: u64) -> usize {
+        (bucket % BUCKET_COUNT) as usize
+    }
+
+    /// Advances the active bucket to match the current time, zeroing every bucket the
+    /// window rolled past so stale counts don't linger into the next read.
+    fn roll(&self) -> u64 {
+        let now_bucket = monotonic_nanos() / self.bucket_nanos;
+        let mut last_bucket = self.current_bucket.load(Ordering::Acquire);
+
+        while now_bucket > last_bucket {
+            let stale = (now_bucket - last_bucket).min(BUCKET_COUNT);
+            match self.current_bucket.compare_exchange_weak(last_bucket, now_bucket, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => {
+                    for step in 1..=stale {
+                        self.buckets[self.bucket_index(last_bucket + step)].store(0, Ordering::Release);
+                    }
+                    break;
+                }
+                Err(current) => last_bucket = current,
+            }
+        }
+
+        now_bucket
+    }
+
+    pub fn increment(&self) {
+        let bucket = self.roll();
+        self.buckets[self.bucket_index(bucket)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Events per second averaged over the live window. During warm-up (less than a
+    /// full window has elapsed since the counter was created) this divides by the
+    /// elapsed time so far instead of the full window, so the rate doesn't read
+    /// artificially low while the window is still filling in; `bucket_nanos` floors the
+    /// divisor so a zero-length window (`with_window(Duration::ZERO)`) can't yield
+    /// `inf`/`NaN`.
+    pub fn rate(&self) -> f64 {
+        self.roll();
+        let total: u64 = self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).sum();
+        let elapsed_nanos = monotonic_nanos().saturating_sub(self.created_nanos).min(self.window_nanos);
+        let divisor_nanos = elapsed_nanos.max(self.bucket_nanos);
+        total as f64 / (divisor_nanos as f64 / 1e9f64)
+    }
+}
+
+impl Default for RateCounter {
+    fn default() -> Self {
+        RateCounter::with_window(Duration::from_secs(60))
+    }
+}
+
+impl Counter for RateCounter {
+    fn increment(&self) {
+        RateCounter::increment(self);
+    }
+
+    fn snapshot(&self) -> snapshot::CounterSnapshot {
+        snapshot::CounterSnapshot::Rate { events_per_second: self.rate() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn rate_divides_by_elapsed_during_warm_up() {
+        let counter = RateCounter::with_window(Duration::from_secs(60));
+        counter.increment();
+
+        // A single event right after creation, divided by the full 60s window, would
+        // read ~0.017/s; divided by elapsed-so-far it reads far higher.
+        assert!(counter.rate() > 1.0);
+    }
+
+    #[test]
+    fn rate_guards_zero_length_window() {
+        let counter = RateCounter::with_window(Duration::ZERO);
+        counter.increment();
+
+        assert!(counter.rate().is_finite());
+    }
+
+    #[test]
+    fn rate_ages_out_stale_buckets_after_window_rolls_over() {
+        let counter = RateCounter::with_window(Duration::from_millis(50));
+        counter.increment();
+        counter.increment();
+
+        thread::sleep(Duration::from_millis(150));
+
+        assert_eq!(counter.rate(), 0f64);
+    }
+}
@@ -1,15 +1,19 @@
 use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
-use std::sync::Arc;
-use crate::Counter;
+use std::sync::{Arc, Weak};
+use crate::{Counter, CounterSnapshot};
 use std::error::Error;
 use std::fmt::{Display, Formatter, Error as FmtError };
 
+/// A registry only holds weak handles to its counters: the strong `Arc<T>` returned by
+/// `Entry::add_counter` is what keeps a counter alive, so dropping every strong handle
+/// elsewhere lets the registry (and anything sampling it, such as `Runner`) observe that
+/// the counter is gone instead of holding it open forever.
 #[derive(Debug, Default)]
-pub struct Registry (pub Arc<HashMap<String,Box<dyn Counter>>>);
+pub struct Registry (pub Arc<HashMap<String, Weak<dyn Counter>>>);
 
 impl Deref for Registry {
-    type Target = HashMap<String, Box<dyn Counter>>;
+    type Target = HashMap<String, Weak<dyn Counter>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -21,26 +25,39 @@ impl Registry {
          Registry::default()
     }
 
-    pub fn from_entries(entries: HashMap<String, Box<dyn Counter>>) -> Self {
+    pub fn from_entries(entries: HashMap<String, Weak<dyn Counter>>) -> Self {
         let counters = entries.into_iter().map(|entry| entry).collect();
 
         Registry(Arc::new(counters))
     }
 
-    pub fn count(&self, name: &str) -> Result<(), CounterNotFoundError>{
+    /// Looks up `name` and snapshots its current state, the single-counter equivalent
+    /// of `export()`. Errors only when `name` was never registered; a registered name
+    /// whose counter has since been dropped returns `Ok(None)`, the same "expected
+    /// race, not a fault" treatment `export()` gives a stale weak handle.
+    pub fn count(&self, name: &str) -> Result<Option<CounterSnapshot>, CounterNotFoundError> {
         let counter = self.0.get(name).ok_or_else(|| CounterNotFoundError {counter: name.to_owned()})?;
 
-        Ok(())
+        Ok(counter.upgrade().map(|counter| counter.snapshot()))
+    }
 
+    /// Walks every live counter and snapshots its current state into a name-keyed map
+    /// that's ready to hand to `serde` for a JSON dump or a binary export. Entries whose
+    /// counter has already been dropped are skipped rather than erroring, since a weak
+    /// handle going stale mid-walk is an expected race, not a fault.
+    pub fn export(&self) -> HashMap<String, CounterSnapshot> {
+        self.0.iter()
+            .filter_map(|(name, counter)| counter.upgrade().map(|counter| (name.clone(), counter.snapshot())))
+            .collect()
     }
 
 }
 
 #[derive(Debug, Default)]
-pub struct Entry(pub HashMap<String, Box<dyn Counter>>);
+pub struct Entry(pub HashMap<String, Weak<dyn Counter>>);
 
 impl Deref for Entry {
-    type Target = HashMap<String, Box<dyn Counter>>;
+    type Target = HashMap<String, Weak<dyn Counter>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -54,8 +71,13 @@ impl DerefMut for Entry {
 }
 
 impl Entry {
-    pub fn add_counter<T: Counter + Default + 'static>(&mut self, name: &str) {
-        self.0.insert(name.to_owned(), Box::new(T::default()));
+    /// Creates the counter, registers a weak handle to it under `name`, and hands the
+    /// caller the strong `Arc<T>` they should hold on to and record through.
+    pub fn add_counter<T: Counter + Default + 'static>(&mut self, name: &str) -> Arc<T> {
+        let counter = Arc::new(T::default());
+        let weak: Weak<dyn Counter> = Arc::downgrade(&counter);
+        self.0.insert(name.to_owned(), weak);
+        counter
     }
 
     pub fn bind(self) -> Registry {
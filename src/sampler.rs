@@ -0,0 +1,92 @@
+use std::convert::TryInto;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::registry::Registry;
+use crate::monotonic_nanos;
+
+/// Gates a periodic action behind a fixed interval using a single `AtomicU64` of the
+/// last-sampled monotonic instant, so that spurious wakeups between samples are a cheap
+/// load-and-compare rather than the expensive walk the interval actually guards.
+struct AtomicInterval {
+    period_nanos: u64,
+    last_nanos: AtomicU64,
+}
+
+impl AtomicInterval {
+    fn new(period: Duration) -> Self {
+        AtomicInterval {
+            period_nanos: period.as_nanos().try_into().unwrap_or(u64::MAX),
+            last_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns `true` (and claims the slot) if `now` is due for the next sample.
+    fn try_claim(&self, now_nanos: u64) -> bool {
+        let last = self.last_nanos.load(Ordering::Acquire);
+        if now_nanos.saturating_sub(last) < self.period_nanos {
+            return false;
+        }
+
+        self.last_nanos.compare_exchange(last, now_nanos, Ordering::AcqRel, Ordering::Acquire).is_ok()
+    }
+}
+
+/// Periodically walks a `Registry`'s counters on a background thread and hands each
+/// one's snapshot to a user-supplied callback. Holds only a `Weak<Registry>`, and
+/// upgrades each counter's own weak handle right before firing the callback, so neither
+/// the registry nor an individual counter is kept alive past its last strong owner.
+pub struct Runner {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Runner {
+    pub fn new<F>(registry: &Arc<Registry>, period: Duration, on_sample: F) -> Self
+    where
+        F: Fn(&str, crate::CounterSnapshot) + Send + Sync + 'static,
+    {
+        let registry = Arc::downgrade(registry);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_thread = Arc::clone(&stop);
+
+        let handle = thread::Builder::new()
+            .name("perfometer-sampler".to_owned())
+            .spawn(move || run(registry, AtomicInterval::new(period), stop_thread, on_sample))
+            .expect("failed to spawn perfometer sampler thread");
+
+        Runner { stop, handle: Some(handle) }
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run<F>(registry: Weak<Registry>, interval: AtomicInterval, stop: Arc<AtomicBool>, on_sample: F)
+where
+    F: Fn(&str, crate::CounterSnapshot) + Send + Sync + 'static,
+{
+    while !stop.load(Ordering::Acquire) {
+        if interval.try_claim(monotonic_nanos()) {
+            let registry = match registry.upgrade() {
+                Some(registry) => registry,
+                None => return,
+            };
+
+            for (name, counter) in registry.iter() {
+                if let Some(counter) = counter.upgrade() {
+                    on_sample(name, counter.snapshot());
+                }
+            }
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,258 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::{now_nanos, snapshot, Clock, Counter, Header};
+
+/// Fixed shard count: high enough that real-world thread counts rarely contend for a
+/// shard, without tying the layout to the host's core count.
+const SHARD_COUNT: usize = 64;
+
+/// Hands out stable per-thread shard indices from a free-list, reclaiming an index when
+/// the thread that held it terminates so indices are reused rather than handed out
+/// unbounded as threads come and go.
+struct ShardIndexPool {
+    free: Mutex<Vec<usize>>,
+    next: AtomicUsize,
+}
+
+impl ShardIndexPool {
+    const fn new() -> Self {
+        ShardIndexPool { free: Mutex::new(Vec::new()), next: AtomicUsize::new(0) }
+    }
+
+    fn acquire(&self) -> usize {
+        if let Some(index) = self.free.lock().unwrap().pop() {
+            return index;
+        }
+        self.next.fetch_add(1, Ordering::Relaxed) % SHARD_COUNT
+    }
+
+    fn release(&self, index: usize) {
+        self.free.lock().unwrap().push(index);
+    }
+}
+
+static SHARD_POOL: ShardIndexPool = ShardIndexPool::new();
+
+struct ShardHandle(usize);
+
+impl Drop for ShardHandle {
+    fn drop(&mut self) {
+        SHARD_POOL.release(self.0);
+    }
+}
+
+thread_local! {
+    static SHARD_HANDLE: ShardHandle = ShardHandle(SHARD_POOL.acquire());
+}
+
+fn shard_index() -> usize {
+    SHARD_HANDLE.with(|handle| handle.0)
+}
+
+/// A single shard's counter, padded out to its own cache line so that two threads
+/// bumping adjacent shards never bounce the same line between cores.
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct EventShard {
+    count: AtomicU64,
+}
+
+/// An `EventCounter` split into per-thread shards, so high-frequency `increment()` calls
+/// from different threads don't contend on the same cache line while at most
+/// `SHARD_COUNT` threads hold a shard (past that, indices wrap and shards are shared): each thread picks its
+/// shard once and `fetch_add`s into only that shard with `Relaxed` ordering. Reads sum
+/// every shard.
+#[derive(Debug)]
+pub struct ShardedEventCounter {
+    headers: Header,
+    shards: Vec<EventShard>,
+}
+
+impl Default for ShardedEventCounter {
+    fn default() -> Self {
+        ShardedEventCounter {
+            headers: Header::default(),
+            shards: (0..SHARD_COUNT).map(|_| EventShard::default()).collect(),
+        }
+    }
+}
+
+impl ShardedEventCounter {
+    pub fn increment(&self) {
+        self.shards[shard_index()].count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.count.load(Ordering::Relaxed)).sum()
+    }
+}
+
+impl Counter for ShardedEventCounter {
+    fn increment(&self) {
+        ShardedEventCounter::increment(self);
+    }
+
+    fn set_count(&self, count: u64) {
+        for shard in &self.shards {
+            shard.count.store(0, Ordering::Relaxed);
+        }
+        self.shards[0].count.store(count, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> snapshot::CounterSnapshot {
+        snapshot::CounterSnapshot::Count(self.count())
+    }
+}
+
+/// A single shard's running elapsed-time total, padded to its own cache line.
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct ElapsedShard {
+    count: AtomicU64,
+    total: AtomicU64,
+}
+
+/// An `ElapsedCounter`'s totals (count + sum of elapsed time) split into per-thread
+/// shards for the same reason as `ShardedEventCounter`. `begin`/`end` still share a
+/// single `time_start`, since pairing them up is inherently sequential per call site;
+/// only the hot running totals are sharded.
+#[derive(Debug)]
+pub struct ShardedElapsedCounter {
+    headers: Header,
+    clock: Clock,
+    time_start: AtomicU64,
+    shards: Vec<ElapsedShard>,
+}
+
+impl Default for ShardedElapsedCounter {
+    fn default() -> Self {
+        ShardedElapsedCounter {
+            headers: Header::default(),
+            clock: Clock::default(),
+            time_start: AtomicU64::new(0),
+            shards: (0..SHARD_COUNT).map(|_| ElapsedShard::default()).collect(),
+        }
+    }
+}
+
+impl ShardedElapsedCounter {
+    /// Creates a `ShardedElapsedCounter` that times against `clock` instead of the
+    /// default `Clock::Monotonic`.
+    pub fn with_clock(clock: Clock) -> Self {
+        ShardedElapsedCounter { clock, ..Default::default() }
+    }
+
+    fn record(&self, elapsed: u64) {
+        let shard = &self.shards[shard_index()];
+        shard.total.fetch_add(elapsed, Ordering::Relaxed);
+        shard.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.count.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.total.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn mean(&self) -> f64 {
+        let count = self.count();
+        if count == 0 {
+            return 0f64;
+        }
+        // Nanoseconds throughout, like every other counter's `mean`/`variance`.
+        self.total() as f64 / count as f64
+    }
+}
+
+impl Counter for ShardedElapsedCounter {
+    fn begin(&self) {
+        self.time_start.store(now_nanos(self.clock), Ordering::Release);
+    }
+
+    fn end(&self) {
+        let time_start = self.time_start.swap(0, Ordering::AcqRel);
+        if time_start == 0 {
+            return;
+        }
+
+        self.record(now_nanos(self.clock) - time_start);
+    }
+
+    fn set_elapsed(&self, elapsed: u64) {
+        if elapsed > 0 {
+            self.record(elapsed);
+        }
+    }
+
+    fn cancel(&self) {
+        self.time_start.store(0, Ordering::Release);
+    }
+
+    fn snapshot(&self) -> snapshot::CounterSnapshot {
+        snapshot::CounterSnapshot::ElapsedTotal {
+            count: self.count(),
+            total: self.total(),
+            mean: self.mean(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn sharded_event_counter_sums_increments_across_threads() {
+        let counter = Arc::new(ShardedEventCounter::default());
+        let threads: Vec<_> = (0..8).map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    counter.increment();
+                }
+            })
+        }).collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(counter.count(), 8000);
+    }
+
+    #[test]
+    fn sharded_event_counter_set_count_resets_every_shard() {
+        let counter = ShardedEventCounter::default();
+        counter.increment();
+        counter.increment();
+
+        counter.set_count(5);
+
+        assert_eq!(counter.count(), 5);
+    }
+
+    #[test]
+    fn sharded_elapsed_counter_sums_total_and_mean_across_threads() {
+        let counter = Arc::new(ShardedElapsedCounter::default());
+        let threads: Vec<_> = (0..4).map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    counter.set_elapsed(50);
+                }
+            })
+        }).collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(counter.count(), 400);
+        assert_eq!(counter.total(), 400 * 50);
+        assert_eq!(counter.mean(), 50f64);
+    }
+}
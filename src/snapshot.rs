@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// A point-in-time, typed read of a counter's state, suitable for handing to a
+/// reporting callback or for export without holding a reference to the counter itself.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CounterSnapshot {
+    /// An integer total: `EventCounter` and the sharded event counter, but also any
+    /// instantaneous gauge/count a caller feeds through `set_count`.
+    Count(u64),
+    /// A duration distribution, shared by `ElapsedCounter` and `IntervalCounter`.
+    Distribution {
+        count: u64,
+        min: u64,
+        max: u64,
+        mean: f64,
+        variance: f64,
+    },
+    /// Bucketed latency samples from a `HistogramCounter`.
+    Histogram {
+        count: u64,
+        min: u64,
+        max: u64,
+        buckets: Vec<u64>,
+    },
+    /// Sharded elapsed-time totals, which only track the aggregate count/total/mean.
+    ElapsedTotal {
+        count: u64,
+        total: u64,
+        mean: f64,
+    },
+    /// A sliding-window rate, in events per second.
+    Rate { events_per_second: f64 },
+    /// The counter doesn't yet know how to describe itself.
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(snapshot: CounterSnapshot) {
+        let json = serde_json::to_string(&snapshot).expect("serialize");
+        let decoded: CounterSnapshot = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(decoded, snapshot);
+    }
+
+    #[test]
+    fn every_variant_round_trips_through_json() {
+        round_trip(CounterSnapshot::Count(42));
+        round_trip(CounterSnapshot::Distribution { count: 3, min: 1, max: 5, mean: 2.5, variance: 0.75 });
+        round_trip(CounterSnapshot::Histogram { count: 2, min: 1, max: 2, buckets: vec![1, 1, 0] });
+        round_trip(CounterSnapshot::ElapsedTotal { count: 4, total: 400, mean: 100.0 });
+        round_trip(CounterSnapshot::Rate { events_per_second: 12.5 });
+        round_trip(CounterSnapshot::Unknown);
+    }
+}